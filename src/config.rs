@@ -0,0 +1,88 @@
+use std::env;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+/// Environment variable naming the base config file to read.
+const CONFIG_ENV: &str = "HASHTAG_IMPORTER_CONFIG";
+/// Environment variable selecting a `config.{profile}.toml` variant (e.g. `production`).
+const PROFILE_ENV: &str = "HASHTAG_IMPORTER_PROFILE";
+/// Environment variable overlaying `server`.
+const SERVER_ENV: &str = "HASHTAG_IMPORTER_SERVER";
+/// Environment variable overlaying `auth.token`, so secrets never need to touch disk.
+const AUTH_TOKEN_ENV: &str = "HASHTAG_IMPORTER_AUTH_TOKEN";
+
+/// Parsed `config.toml`: the local Mastodon target, its credentials and the hashtags to mirror.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct Config {
+    #[serde(default)]
+    pub server: String,
+    #[serde(default)]
+    pub auth: Auth,
+    #[serde(default)]
+    pub hashtag: Vec<Hashtag>,
+    /// How many timeline pages to walk through the `Link` header per fetch; `None` means a
+    /// single page, as before pagination existed.
+    pub max_pages: Option<u32>,
+    /// Remote instances we refuse to contact or import from. Matching is by exact host or
+    /// parent-domain suffix, so `example.com` also covers `mastodon.example.com`.
+    pub blocklist: Option<Vec<String>>,
+    /// When present and non-empty, the only remote instances we will contact; everything
+    /// else is skipped.
+    pub allowlist: Option<Vec<String>>,
+}
+
+/// OAuth application credentials and user token for our own server.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct Auth {
+    #[serde(default)]
+    pub client_id: String,
+    #[serde(default)]
+    pub client_secret: String,
+    #[serde(default)]
+    pub token: String,
+}
+
+/// A hashtag to mirror, the remote instances to pull it from, and optional `any[]` variants.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Hashtag {
+    pub name: String,
+    pub any: Option<Vec<String>>,
+    #[serde(default)]
+    pub sources: Vec<String>,
+}
+
+/// Load the configuration in layers: read the base TOML file (chosen by `$HASHTAG_IMPORTER_CONFIG`
+/// or the `$HASHTAG_IMPORTER_PROFILE` variant, defaulting to `config.toml`), then overlay
+/// environment variables so secrets like the auth token can come from the environment rather
+/// than a file baked into a container image. Environment values win over the file.
+pub(crate) fn load_config() -> Result<Config> {
+    let path = config_path();
+    let contents =
+        std::fs::read_to_string(&path).with_context(|| format!("cannot read config file {path}"))?;
+    let mut config: Config =
+        toml::from_str(&contents).with_context(|| format!("cannot parse config file {path}"))?;
+    if let Ok(server) = env::var(SERVER_ENV) {
+        config.server = server;
+    }
+    if let Ok(token) = env::var(AUTH_TOKEN_ENV) {
+        config.auth.token = token;
+    }
+    if config.server.is_empty() {
+        bail!("no server configured: set it in {path} or ${SERVER_ENV}");
+    }
+    Ok(config)
+}
+
+/// Pick the base config file from the environment: an explicit `$HASHTAG_IMPORTER_CONFIG`
+/// path wins, otherwise a `$HASHTAG_IMPORTER_PROFILE` selects `config.{profile}.toml`, falling
+/// back to `config.toml`.
+fn config_path() -> String {
+    if let Ok(path) = env::var(CONFIG_ENV) {
+        return path;
+    }
+    match env::var(PROFILE_ENV) {
+        Ok(profile) if !profile.is_empty() => format!("config.{profile}.toml"),
+        _ => "config.toml".to_string(),
+    }
+}
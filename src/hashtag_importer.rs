@@ -1,14 +1,16 @@
-use std::collections::HashSet;
-use std::hash::Hash;
+use std::collections::{HashMap, HashSet};
 use std::io;
 use std::io::Write;
-use std::thread::sleep;
-use std::time::{Duration, Instant};
+use std::sync::Mutex;
+use std::time::Duration;
 
 use core::num::NonZeroU32;
 
 use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
 use governor::{Quota, RateLimiter};
+use texting_robots::Robot;
 
 use crate::config::*;
 use crate::types::*;
@@ -17,8 +19,167 @@ const USER_AGENT: &str = concat!("hashtag-importer v", env!("CARGO_PKG_VERSION")
 const CLIENT_NAME: &str = "hashtag-importer";
 const CLIENT_WEBSITE: &str = "https://github.com/anisse/hashtag-importer";
 
-fn client() -> Result<reqwest::blocking::Client> {
-    reqwest::blocking::Client::builder()
+// How many per-host fetches/imports to keep in flight at once; the keyed rate
+// limiters still bound how often any single host is actually hit.
+const CONCURRENCY: usize = 8;
+
+// Keep a small buffer of calls in reserve rather than racing the bucket to exactly 0.
+const RATE_LIMIT_MARGIN: u32 = 2;
+
+/// Last rate-limit state a host advertised through its `X-RateLimit-*` headers.
+#[derive(Default)]
+struct HostLimit {
+    limit: Option<u32>,
+    remaining: Option<u32>,
+    reset: Option<DateTime<Utc>>,
+}
+
+impl HostLimit {
+    /// A host has headroom once it has advertised its window ceiling and still reports
+    /// comfortably more than our safety margin left in it. When that holds we trust the
+    /// server's own pacing and skip the conservative static quota.
+    fn has_headroom(&self) -> bool {
+        self.limit.is_some() && self.remaining.map_or(false, |r| r > RATE_LIMIT_MARGIN)
+    }
+}
+
+/// Per-host view of Mastodon's advertised rate limits, driven by real responses
+/// rather than the static `Quota`s guessed at in `run_loop()`.
+type RateLimits = Mutex<HashMap<String, HostLimit>>;
+
+/// Snoop a response's `X-RateLimit-*` headers and remember when `host`'s bucket refills.
+/// Gracefully does nothing when the server doesn't advertise these headers.
+fn record_host_limit(lim_headers: &RateLimits, host: &str, response: &reqwest::Response) {
+    let headers = response.headers();
+    let limit = headers
+        .get("X-RateLimit-Limit")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u32>().ok());
+    let remaining = headers
+        .get("X-RateLimit-Remaining")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u32>().ok());
+    let reset = headers
+        .get("X-RateLimit-Reset")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| DateTime::parse_from_rfc3339(v).ok())
+        .map(|t| t.with_timezone(&Utc));
+    if limit.is_none() && remaining.is_none() && reset.is_none() {
+        return;
+    }
+    let mut map = lim_headers.lock().unwrap();
+    let entry = map.entry(host.to_string()).or_default();
+    if limit.is_some() {
+        entry.limit = limit;
+    }
+    if remaining.is_some() {
+        entry.remaining = remaining;
+    }
+    if reset.is_some() {
+        entry.reset = reset;
+    }
+}
+
+/// Whether `host` currently advertises enough headroom to bypass the static query quota.
+fn host_has_headroom(lim_headers: &RateLimits, host: &str) -> bool {
+    lim_headers
+        .lock()
+        .unwrap()
+        .get(host)
+        .map_or(false, HostLimit::has_headroom)
+}
+
+/// Block until `host`'s advertised window refills, if it reports `RATE_LIMIT_MARGIN` or
+/// fewer calls left.
+async fn wait_for_host(lim_headers: &RateLimits, host: &str) {
+    let reset = {
+        let map = lim_headers.lock().unwrap();
+        match map.get(host) {
+            Some(h) if h.remaining.map_or(false, |r| r <= RATE_LIMIT_MARGIN) => h.reset,
+            _ => None,
+        }
+    };
+    if let Some(reset) = reset {
+        if let Ok(delay) = (reset - Utc::now()).to_std() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Per-host robots.txt cache. `None` means we couldn't fetch or parse one, which
+/// we treat as "allowed, no extra delay" so vanilla Mastodon keeps working.
+type Robots = Mutex<HashMap<String, Option<Robot>>>;
+
+/// Fetch and cache `host`'s robots.txt on first contact, parsed for our `USER_AGENT`.
+async fn fetch_robots(robots: &Robots, host: &str) {
+    if robots.lock().unwrap().contains_key(host) {
+        return;
+    }
+    let body = match client() {
+        Ok(c) => match c.get(format!("https://{host}/robots.txt")).send().await {
+            Ok(r) => r.text().await.ok(),
+            Err(_) => None,
+        },
+        Err(_) => None,
+    };
+    let robot = body.and_then(|body| Robot::new(USER_AGENT, body.as_bytes()).ok());
+    robots.lock().unwrap().insert(host.to_string(), robot);
+}
+
+/// Whether `url` may be crawled on `host`, plus any `Crawl-delay` the host requests.
+fn robots_check(robots: &Robots, host: &str, url: &str) -> (bool, Option<Duration>) {
+    let map = robots.lock().unwrap();
+    match map.get(host) {
+        Some(Some(robot)) => (
+            robot.allowed(url),
+            robot.delay.map(|d| Duration::from_secs_f64(d as f64)),
+        ),
+        _ => (true, None),
+    }
+}
+
+/// Extract the `rel="next"` URL from a Mastodon `Link` response header, if present.
+fn next_link(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    let link = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+    for part in link.split(',') {
+        let mut segments = part.split(';');
+        let url = segments
+            .next()?
+            .trim()
+            .trim_start_matches('<')
+            .trim_end_matches('>');
+        if segments.any(|s| s.trim() == "rel=\"next\"") {
+            return Some(url.to_string());
+        }
+    }
+    None
+}
+
+/// Whether the operator's allow/block lists permit contacting `host`.
+/// Matching is exact or by parent-domain suffix, so blocking `example.com`
+/// also blocks `mastodon.example.com`. An allowlist, when non-empty, is exclusive.
+fn host_allowed(config: &Config, host: &str) -> bool {
+    fn matches(list: &[String], host: &str) -> bool {
+        list.iter().any(|domain| {
+            let domain = domain.trim_start_matches('.');
+            host == domain || host.ends_with(&format!(".{domain}"))
+        })
+    }
+    if let Some(allow) = &config.allowlist {
+        if !allow.is_empty() && !matches(allow, host) {
+            return false;
+        }
+    }
+    if let Some(block) = &config.blocklist {
+        if matches(block, host) {
+            return false;
+        }
+    }
+    true
+}
+
+fn client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
         .timeout(Duration::from_secs(20))
         .user_agent(USER_AGENT)
         .cookie_store(true)
@@ -35,19 +196,24 @@ pub(crate) fn create_app() -> Result<()> {
         .context("unable to read stdin")?;
     let url = reqwest::Url::parse(format!("https://{server_domain}/").as_str())
         .with_context(|| format!("{server_domain} is not a domain"))?;
+    let rt = tokio::runtime::Runtime::new().context("cannot build tokio runtime")?;
     // Register the app
-    let resp: ApplicationResponse = client()?
-        .post(url.join("api/v1/apps")?)
-        .json(&ApplicationRegistration {
-            client_name: CLIENT_NAME,
-            redirect_uris: OOB_URI,
-            website: CLIENT_WEBSITE,
-            scopes: Scope::Read,
-        })
-        .send()
-        .context("create app post failed")?
-        .json()
-        .context("create app response body not valid json")?;
+    let resp: ApplicationResponse = rt.block_on(async {
+        client()?
+            .post(url.join("api/v1/apps")?)
+            .json(&ApplicationRegistration {
+                client_name: CLIENT_NAME,
+                redirect_uris: OOB_URI,
+                website: CLIENT_WEBSITE,
+                scopes: Scope::Read,
+            })
+            .send()
+            .await
+            .context("create app post failed")?
+            .json()
+            .await
+            .context("create app response body not valid json")
+    })?;
     dbg!(&resp);
     println!("Copy paste this into your config.toml:");
     println!("[auth]");
@@ -57,7 +223,7 @@ pub(crate) fn create_app() -> Result<()> {
 }
 
 pub(crate) fn user_auth() -> Result<()> {
-    let config = load_config("config.toml")?;
+    let config = load_config()?;
     println!("Open this link in your web browser to give the app read permission from your user account:
 https://{}/oauth/authorize?response_type=code&client_id={}&redirect_uri=urn:ietf:wg:oauth:2.0:oob&scope=read",
         config.server, config.auth.client_id,
@@ -67,12 +233,15 @@ https://{}/oauth/authorize?response_type=code&client_id={}&redirect_uri=urn:ietf
     io::stdin()
         .read_line(&mut code)
         .context("unable to read stdin")?;
-    let token = token(
+    let rt = tokio::runtime::Runtime::new().context("cannot build tokio runtime")?;
+    let lim_headers = RateLimits::default();
+    let token = rt.block_on(token(
         &config.server,
         &config.auth.client_id,
         &config.auth.client_secret,
         &code.trim().to_string(),
-    )?;
+        &lim_headers,
+    ))?;
     println!("Update your config.toml auth section:");
     println!("[auth]");
     println!("token = '{token}'");
@@ -80,14 +249,24 @@ https://{}/oauth/authorize?response_type=code&client_id={}&redirect_uri=urn:ietf
 }
 
 pub(crate) fn run() -> Result<()> {
-    let config = load_config("config.toml")?;
+    let rt = tokio::runtime::Runtime::new().context("cannot build tokio runtime")?;
+    rt.block_on(run_loop())
+}
+
+async fn run_loop() -> Result<()> {
+    let config = load_config()?;
+    if config.auth.token.is_empty() {
+        bail!("no auth token configured: set it in the config file or $HASHTAG_IMPORTER_AUTH_TOKEN");
+    }
     println!(
         "{} hashtags in config: {:?}",
         config.hashtag.len(),
         config.hashtag.iter().map(|h| &h.name).collect::<Vec<_>>()
     );
     // Rate limiters
-    // Only one query (hashtag fetch or import) per minute on all servers
+    // Conservative fallback of one query (hashtag fetch or import) per minute per host, used
+    // while that host has not advertised its real `X-RateLimit-*` budget or is running low on
+    // it; see `wait_for_query`.
     let lim_queries = RateLimiter::keyed(Quota::per_minute(NonZeroU32::new(1).unwrap()));
     // At most 5 post imports per remote instance per hour
     let lim_upstreams = RateLimiter::keyed(Quota::per_hour(NonZeroU32::new(5).unwrap()));
@@ -95,83 +274,165 @@ pub(crate) fn run() -> Result<()> {
     let lim_import = RateLimiter::direct(Quota::per_hour(NonZeroU32::new(20).unwrap()));
     // At most 4 runs per hour (average of 15min between runs)
     let lim_loop = RateLimiter::direct(Quota::per_hour(NonZeroU32::new(4).unwrap()));
-    let mut imported_statuses: Vec<HashSet<String>> = vec![HashSet::new(); config.hashtag.len()];
+    // Adaptive, per-host limits learned from each server's X-RateLimit-* headers.
+    let lim_headers = RateLimits::default();
+    // Per-host robots.txt, so we behave like a polite crawler against other instances.
+    let robots = Robots::default();
+    // Per-hashtag dedup set, shared so concurrent import tasks can update it.
+    let imported_statuses: Vec<Mutex<HashSet<String>>> = (0..config.hashtag.len())
+        .map(|_| Mutex::new(HashSet::new()))
+        .collect();
     loop {
         for (i, hashtag) in config.hashtag.iter().enumerate() {
             if let Err(e) = import_hashtag(
                 &config,
                 hashtag,
-                &mut imported_statuses[i],
+                &imported_statuses[i],
                 &lim_queries,
                 &lim_upstreams,
                 &lim_import,
-            ) {
+                &lim_headers,
+                &robots,
+            )
+            .await
+            {
                 println!("Hashtag {}: {e:#}", hashtag.name);
                 continue;
             }
         }
         print!(".");
         let _ = io::stdout().flush(); // we really don't care if it fails
-        sleep(Duration::from_secs(5 * 60));
-        wait_until(&lim_loop);
+        tokio::time::sleep(Duration::from_secs(5 * 60)).await;
+        wait_until(&lim_loop).await;
         // This one can grow unbounded, shrink it to cleanup status
         lim_upstreams.shrink_to_fit();
     }
 }
 
-fn import_hashtag(
+async fn import_hashtag(
     config: &Config,
     hashtag: &Hashtag,
-    imported_statuses: &mut HashSet<String>,
+    imported_statuses: &Mutex<HashSet<String>>,
     lim_queries: &governor::DefaultKeyedRateLimiter<String>,
     lim_upstreams: &governor::DefaultKeyedRateLimiter<String>,
     lim_import: &governor::DefaultDirectRateLimiter,
+    lim_headers: &RateLimits,
+    robots: &Robots,
 ) -> Result<()> {
-    let mut remote_statuses: HashSet<String> = HashSet::new();
-    for server in hashtag.sources.iter() {
-        wait_until_key(lim_queries, server);
-        let list = hashtags(server, "", &hashtag.name, &hashtag.any, 25)
-            .with_context(|| format!("fetch remote {server} error"))?;
-        remote_statuses.extend(list.into_iter().map(|s| s.url));
-    }
+    // Fetch every source timeline concurrently; one slow host no longer blocks the rest.
+    let remote_statuses: HashSet<String> = futures::stream::iter(hashtag.sources.iter())
+        .map(|server| async move {
+            if !host_allowed(config, server) {
+                println!(
+                    "Hashtag {}: skipping {server}: blocked by allow/block list",
+                    hashtag.name
+                );
+                return Vec::new();
+            }
+            fetch_robots(robots, server).await;
+            let url = format!("https://{server}/api/v1/timelines/tag/{}", hashtag.name);
+            let (allowed, crawl_delay) = robots_check(robots, server, &url);
+            if !allowed {
+                println!(
+                    "Hashtag {}: skipping {server}: disallowed by robots.txt",
+                    hashtag.name
+                );
+                return Vec::new();
+            }
+            match hashtags(
+                server,
+                "",
+                &hashtag.name,
+                &hashtag.any,
+                25,
+                config.max_pages,
+                lim_queries,
+                lim_headers,
+                crawl_delay,
+            )
+            .await
+            {
+                Ok(list) => list.into_iter().map(|s| s.url).collect(),
+                Err(e) => {
+                    println!("Hashtag {}: fetch remote {server} error: {e:#}", hashtag.name);
+                    Vec::new()
+                }
+            }
+        })
+        .buffer_unordered(CONCURRENCY)
+        .collect::<Vec<Vec<String>>>()
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
     /* Because of the way Mastodon IDs work, we cannot kindly ask the server to give us posts
      * 'since_id': the snowflake ID variant used by mastodon contains the timestamp of the
      * post. So importing remote posts older than the latest local post means we won't see them
      * on the next iteration if we use since_id.
      */
-    wait_until_key(lim_queries, &config.server);
     let list = hashtags(
         &config.server,
         &config.auth.token,
         &hashtag.name,
         &hashtag.any,
         40,
+        config.max_pages,
+        lim_queries,
+        lim_headers,
+        None,
     )
+    .await
     .with_context(|| format!("fetch local {} error", config.server))?;
     let local_statuses: HashSet<String> = HashSet::from_iter(list.into_iter().map(|s| s.url));
-    for status in remote_statuses.difference(&local_statuses) {
-        if imported_statuses.contains(status) {
-            continue;
-        }
-        if let Err(e) = import_status(status, config, lim_queries, lim_upstreams, lim_import) {
-            println!("Hashtag {}: skipping {status} : {e:#}", hashtag.name);
-            continue;
-        }
-        println!("Hashtag {}: imported {status}", hashtag.name);
-        imported_statuses.insert(status.to_string());
-    }
+    let candidates: Vec<String> = {
+        let imported = imported_statuses.lock().unwrap();
+        remote_statuses
+            .difference(&local_statuses)
+            .filter(|s| !imported.contains(*s))
+            .cloned()
+            .collect()
+    };
+    // Import the missing statuses concurrently, bounded by the per-host limiters.
+    futures::stream::iter(candidates.into_iter())
+        .map(|status| async move {
+            match import_status(
+                &status,
+                config,
+                lim_queries,
+                lim_upstreams,
+                lim_import,
+                lim_headers,
+                robots,
+            )
+            .await
+            {
+                Ok(()) => {
+                    println!("Hashtag {}: imported {status}", hashtag.name);
+                    imported_statuses.lock().unwrap().insert(status);
+                }
+                Err(e) => println!("Hashtag {}: skipping {status} : {e:#}", hashtag.name),
+            }
+        })
+        .buffer_unordered(CONCURRENCY)
+        .collect::<Vec<()>>()
+        .await;
     // Keep only the intersection between imported, and seen this iteration.
     // This is to prevent imported_status to grow unbounded
-    imported_statuses.retain(|s| remote_statuses.contains(s));
+    imported_statuses
+        .lock()
+        .unwrap()
+        .retain(|s| remote_statuses.contains(s));
     Ok(())
 }
 
-fn import_status(
+async fn import_status(
     status: &str,
     config: &Config,
     lim_queries: &governor::DefaultKeyedRateLimiter<String>,
     lim_upstreams: &governor::DefaultKeyedRateLimiter<String>,
     lim_import: &governor::DefaultDirectRateLimiter,
+    lim_headers: &RateLimits,
+    robots: &Robots,
 ) -> Result<()> {
     let host = reqwest::Url::parse(status)
         .context("unparseable status url")
@@ -181,33 +442,62 @@ fn import_status(
                 .ok_or(anyhow!("no host"))
         })
         .context("bad url")?;
+    if !host_allowed(config, &host) {
+        bail!("{host} blocked by allow/block list");
+    }
+    fetch_robots(robots, &host).await;
+    // The remote host's Crawl-delay only governs requests to that host; the import below
+    // searches our own `config.server`, so it must not be throttled by a third party's
+    // robots directive.
+    let (allowed, _crawl_delay) = robots_check(robots, &host, status);
+    if !allowed {
+        bail!("disallowed by robots.txt on {host}");
+    }
     if lim_upstreams.check_key(&host).is_err() {
         bail!("for now reached quota for {host}");
     }
-    wait_until(lim_import);
-    wait_until_key(lim_queries, &config.server);
-    import(&config.server, &config.auth.token, status).context("import error ")?;
+    wait_until(lim_import).await;
+    wait_for_query(lim_queries, lim_headers, &config.server, None).await;
+    import(&config.server, &config.auth.token, status, lim_headers)
+        .await
+        .context("import error ")?;
     Ok(())
 }
 
-// This wouldn't be needed if using async
-// TODO: as a trait, maybe
-fn wait_until_key<K>(lim: &governor::DefaultKeyedRateLimiter<K>, key: &K)
-where
-    K: Clone + Hash + Eq,
-{
-    while let Err(e) = lim.check_key(key) {
-        sleep(e.wait_time_from(Instant::now()));
+/// Gate a query to `host`, driven by that server's own `X-RateLimit-*` state rather than
+/// the static guess: when the host advertises ample headroom we trust it and skip the
+/// conservative `lim_queries` quota, only falling back to it for hosts we know nothing about
+/// or that are running low. Either way we block until the window refills if it is down to
+/// the safety margin,
+/// then honour any robots.txt Crawl-delay.
+async fn wait_for_query(
+    lim_queries: &governor::DefaultKeyedRateLimiter<String>,
+    lim_headers: &RateLimits,
+    host: &str,
+    crawl_delay: Option<Duration>,
+) {
+    if !host_has_headroom(lim_headers, host) {
+        lim_queries.until_key_ready(&host.to_string()).await;
     }
-}
-// TODO: as a trait, maybe
-fn wait_until(lim: &governor::DefaultDirectRateLimiter) {
-    while let Err(e) = lim.check() {
-        sleep(e.wait_time_from(Instant::now()));
+    wait_for_host(lim_headers, host).await;
+    // Never poll faster than the host's robots.txt Crawl-delay asks.
+    if let Some(delay) = crawl_delay {
+        tokio::time::sleep(delay).await;
     }
 }
 
-fn token<S: AsRef<str>>(server: S, client_id: S, client_secret: S, code: S) -> Result<String> {
+async fn wait_until(lim: &governor::DefaultDirectRateLimiter) {
+    lim.until_ready().await;
+}
+
+async fn token<S: AsRef<str>>(
+    server: S,
+    client_id: S,
+    client_secret: S,
+    code: S,
+    lim_headers: &RateLimits,
+) -> Result<String> {
+    wait_for_host(lim_headers, server.as_ref()).await;
     let response = client()?
         .post(format!("https://{}/oauth/token", server.as_ref()))
         .json(&TokenQuery {
@@ -219,42 +509,68 @@ fn token<S: AsRef<str>>(server: S, client_id: S, client_secret: S, code: S) -> R
             scope: Some(Scope::Read),
         })
         .send()
-        .context("token post failed")?
-        .with_error_text()?;
-    let token: Token = response.json().context("token body not valid json")?;
+        .await
+        .context("token post failed")?;
+    record_host_limit(lim_headers, server.as_ref(), &response);
+    let response = with_error_text(response).await?;
+    let token: Token = response.json().await.context("token body not valid json")?;
     Ok(token.access_token)
 }
 
-fn hashtags(
+async fn hashtags(
     server: &str,
     token: &str,
     name: &str,
     any: &Option<Vec<String>>,
     limit: u8,
+    max_pages: Option<u32>,
+    lim_queries: &governor::DefaultKeyedRateLimiter<String>,
+    lim_headers: &RateLimits,
+    crawl_delay: Option<Duration>,
 ) -> Result<Vec<Status>> {
-    let response: Vec<Status> = client()?
-        .get(
-            reqwest::Url::parse_with_params(
-                &format!("https://{server}/api/v1/timelines/tag/{name}?limit={limit}"),
-                //"any[]=kr2023&any[]=KernelRecipes2023",
-                any.iter()
-                    .flat_map(|l| l.iter().map(|h| ("any[]", h)))
-                    .collect::<Vec<_>>(),
-            )
-            .with_context(|| format!("hashtags url for {server}"))?
-            .as_str(),
-        )
-        .header("Authorization", format!("Bearer {token}"))
-        .send()
-        .context("hashtags get failed")?
-        .with_error_text()?
-        .json()
-        .context("hash tag statuses body not valid json")?;
-    Ok(response)
+    // First page; subsequent pages come straight from the Link header's `next` url.
+    let mut url = reqwest::Url::parse_with_params(
+        &format!("https://{server}/api/v1/timelines/tag/{name}?limit={limit}"),
+        //"any[]=kr2023&any[]=KernelRecipes2023",
+        any.iter()
+            .flat_map(|l| l.iter().map(|h| ("any[]", h)))
+            .collect::<Vec<_>>(),
+    )
+    .with_context(|| format!("hashtags url for {server}"))?;
+    let budget = max_pages.unwrap_or(1).max(1);
+    let mut statuses: Vec<Status> = Vec::new();
+    for _ in 0..budget {
+        wait_for_query(lim_queries, lim_headers, server, crawl_delay).await;
+        let response = client()?
+            .get(url.clone())
+            .header("Authorization", format!("Bearer {token}"))
+            .send()
+            .await
+            .context("hashtags get failed")?;
+        record_host_limit(lim_headers, server, &response);
+        let next = next_link(response.headers());
+        let page: Vec<Status> = with_error_text(response)
+            .await?
+            .json()
+            .await
+            .context("hash tag statuses body not valid json")?;
+        let page_empty = page.is_empty();
+        statuses.extend(page);
+        // Stop at the budget, an empty page, or when the server offers no next page.
+        match next {
+            Some(next) if !page_empty => {
+                url = reqwest::Url::parse(&next)
+                    .with_context(|| format!("next page url for {server}"))?;
+            }
+            _ => break,
+        }
+    }
+    Ok(statuses)
 }
 
-fn import(server: &str, token: &str, url: &str) -> Result<()> {
-    client()?
+async fn import(server: &str, token: &str, url: &str, lim_headers: &RateLimits) -> Result<()> {
+    wait_for_host(lim_headers, server).await;
+    let response = client()?
         .get(
             reqwest::Url::parse_with_params(
                 &format!("https://{server}/api/v2/search"),
@@ -270,26 +586,23 @@ fn import(server: &str, token: &str, url: &str) -> Result<()> {
         )
         .header("Authorization", format!("Bearer {token}"))
         .send()
-        .context("import get failed")?
-        .with_error_text()?;
+        .await
+        .context("import get failed")?;
+    record_host_limit(lim_headers, server, &response);
+    with_error_text(response).await?;
     Ok(())
 }
 
-trait WithErrorText {
-    fn with_error_text(self) -> Result<Self>
-    where
-        Self: Sized;
-}
-impl WithErrorText for reqwest::blocking::Response {
-    fn with_error_text(self) -> Result<Self> {
-        let status_err = self.error_for_status_ref();
-        if let Err(e) = status_err {
-            bail!(
-                "Got response {}: {e}",
-                self.text()
-                    .with_context(|| format!("Got {e} and cannot read body"))?
-            );
-        }
-        Ok(self)
+/// Turn an error status into an `anyhow` error carrying the response body text.
+async fn with_error_text(response: reqwest::Response) -> Result<reqwest::Response> {
+    if let Err(e) = response.error_for_status_ref() {
+        bail!(
+            "Got response {}: {e}",
+            response
+                .text()
+                .await
+                .with_context(|| format!("Got {e} and cannot read body"))?
+        );
     }
+    Ok(response)
 }